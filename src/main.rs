@@ -1,19 +1,94 @@
 use anyhow::{Context, Result, ensure};
 use clap::Parser;
+use crossbeam_channel::{Receiver, Sender, bounded};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{WalkBuilder, WalkState};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
-use std::fs::{File, read_dir};
+use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{Receiver, Sender, channel};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::thread;
 
+/// Bound on each pipeline channel's buffered items. Caps how far a fast
+/// stage can race ahead of a slower one, providing natural backpressure on
+/// very large `msbuild.log` files.
+const CHANNEL_CAPACITY: usize = 1024;
+
 #[derive(Deserialize, Serialize)]
 struct CompileCommand {
     file: PathBuf,
     directory: PathBuf,
     arguments: Vec<String>,
+    /// Which `--compiler-executable` matched this entry.
+    compiler: String,
+}
+
+/// Serialized compile-commands-database entry. Carries either `arguments`
+/// or `command`, never both, depending on `--format`.
+#[derive(Serialize)]
+struct CompileCommandEntry {
+    file: PathBuf,
+    directory: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    arguments: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    /// Which `--compiler-executable` produced this entry.
+    compiler: String,
+}
+
+impl CompileCommandEntry {
+    fn new(cc: &CompileCommand, format: OutputFormat) -> Self {
+        let (arguments, command) = match format {
+            OutputFormat::Arguments => (Some(cc.arguments.clone()), None),
+            OutputFormat::Command => (None, Some(quote_command(&cc.arguments))),
+        };
+        CompileCommandEntry {
+            file: cc.file.clone(),
+            directory: cc.directory.clone(),
+            arguments,
+            command,
+            compiler: cc.compiler.clone(),
+        }
+    }
+}
+
+/// Shell-quotes and joins `arguments` into the single `command` string form
+/// used by `--format command`.
+///
+/// This is lossy, not a true inverse of `cleanup_line`: by the time
+/// `arguments` reaches this function, `tokenize_lines` has already split the
+/// original log line on whitespace, so a value that was originally quoted to
+/// protect an embedded space (e.g. `"Program Files"`) has already been split
+/// into separate tokens and can't be reconstructed here. The whitespace
+/// check below is a no-op against today's pipeline; it only guards arguments
+/// that already contain embedded whitespace should that upstream invariant
+/// ever change.
+fn quote_command(arguments: &[String]) -> String {
+    arguments
+        .iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.chars().any(char::is_whitespace) {
+                format!("\"{}\"", arg.replace('"', "\\\""))
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `file`/`directory` are emitted as absolute paths or as paths
+/// relative to `--path-base`.
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum PathStyle {
+    Absolute,
+    Relative,
 }
 
 #[derive(Parser)]
@@ -34,9 +109,73 @@ struct Cli {
     #[arg(short('d'), long)]
     source_directory: PathBuf,
 
-    /// Name of compiler executable
+    /// Name of compiler executable; repeatable to match several toolchains
+    /// (e.g. `cl.exe` and `clang-cl.exe`)
     #[arg(short('c'), long, name = "EXE", default_value = "cl.exe")]
-    compiler_executable: String,
+    compiler_executable: Vec<String>,
+
+    /// Only index files matching this extension (e.g. `c`, `cpp`, `h`); repeatable
+    #[arg(short('e'), long = "extension", name = "EXT")]
+    extensions: Vec<String>,
+
+    /// Exclude files matching this glob from the lookup tree; repeatable
+    #[arg(long, name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Don't respect .gitignore/.ignore files when building the lookup tree
+    #[arg(long)]
+    no_ignore: bool,
+
+    /// Include hidden files and directories when building the lookup tree
+    #[arg(long)]
+    hidden: bool,
+
+    /// Run this command template once per compile command, in addition to
+    /// writing the JSON database. Supports `{}` (full path), `{/}` (file
+    /// name), `{//}` (directory), `{.}` (path without extension), `{args}`
+    /// (the captured arguments), and `{compiler}` (the matched compiler)
+    #[arg(short('x'), long)]
+    exec: Option<String>,
+
+    /// Number of parallel jobs to run with `--exec`
+    #[arg(short('j'), long = "threads", default_value_t = default_threads())]
+    threads: usize,
+
+    /// Don't sort output entries by file (then directory); emit them in
+    /// whatever order they were produced, which varies run-to-run
+    #[arg(long)]
+    no_sort: bool,
+
+    /// Emit `directory` as an absolute path (canonicalized; a relative
+    /// directory resolves against the working directory) or as a path
+    /// relative to `--path-base`; a directory outside `--path-base` is not
+    /// rebased. `file` is always the bare file name
+    #[arg(long, value_enum, default_value = "relative")]
+    path_style: PathStyle,
+
+    /// Base directory for `--path-style relative` output (default:
+    /// `--source-directory`)
+    #[arg(long)]
+    path_base: Option<PathBuf>,
+
+    /// Serialize each entry's arguments as the `arguments` array (default)
+    /// or as a single shell-quoted `command` string
+    #[arg(long, value_enum, default_value = "arguments")]
+    format: OutputFormat,
+}
+
+/// Serialized schema for a `CompileCommand`'s captured arguments: the
+/// `arguments` array form, or the single shell-quoted `command` string form.
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum OutputFormat {
+    Arguments,
+    Command,
+}
+
+/// Default `--threads` value: the number of available CPUs, falling back to
+/// a single thread if that can't be determined.
+fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
 }
 
 /// Error handler.  Reports any received errors to `STDERR`.
@@ -46,45 +185,67 @@ fn error_handler(error_rx: Receiver<String>) {
     }
 }
 
-/// Explores the directory tree `path`, visiting all directories, and sending
-/// any files found on the `entry_tx` sender channel. Any IO errors are reported
-/// to the `error_tx` channel.
+/// Builds the `ignore` crate override set used to restrict the directory
+/// traversal to source files. `extensions` are turned into `*.ext` include
+/// globs; `exclude` globs are negated so they're skipped even when they'd
+/// otherwise match an include glob.
+fn build_overrides(
+    root: &Path,
+    extensions: &[String],
+    exclude: &[String],
+) -> Result<Override> {
+    let mut builder = OverrideBuilder::new(root);
+    for ext in extensions {
+        builder
+            .add(&format!("*.{ext}"))
+            .with_context(|| format!("Invalid extension: {ext:?}"))?;
+    }
+    for pattern in exclude {
+        builder
+            .add(&format!("!{pattern}"))
+            .with_context(|| format!("Invalid exclude glob: {pattern:?}"))?;
+    }
+    builder.build().context("Failed to build override set")
+}
+
+/// Explores the directory tree `path` in parallel, respecting `.gitignore`/
+/// `.ignore` rules and hidden-file conventions (unless overridden by
+/// `no_ignore`/`hidden`), and sends any files found on the `entry_tx` sender
+/// channel. Any walk errors are reported to the `error_tx` channel.
 fn find_all_files(
     path: PathBuf,
+    overrides: Override,
+    no_ignore: bool,
+    hidden: bool,
     entry_tx: Sender<PathBuf>,
     error_tx: Sender<String>,
 ) {
-    let mut stack = vec![path];
-    while let Some(path) = stack.pop() {
-        let reader = match read_dir(&path) {
-            Ok(r) => r,
-            Err(e) => {
-                let e = format!("read_dir error for {path:?}: {e}");
-                let _ = error_tx.send(e);
-                continue;
-            }
-        };
-        for entry in reader {
-            let entry = match entry {
-                Ok(de) => de,
+    let walker = WalkBuilder::new(&path)
+        .hidden(!hidden)
+        .ignore(!no_ignore)
+        .git_ignore(!no_ignore)
+        .git_exclude(!no_ignore)
+        .overrides(overrides)
+        .threads(default_threads())
+        .build_parallel();
+
+    walker.run(|| {
+        let entry_tx = entry_tx.clone();
+        let error_tx = error_tx.clone();
+        Box::new(move |result| {
+            match result {
+                Ok(entry) => {
+                    if entry.file_type().is_some_and(|ft| ft.is_file()) {
+                        let _ = entry_tx.send(entry.into_path());
+                    }
+                }
                 Err(e) => {
-                    let e = format!("Failed to read from {path:?}: {e}",);
-                    let _ = error_tx.send(e);
-                    continue;
+                    let _ = error_tx.send(format!("walk error: {e}"));
                 }
-            };
-
-            let path = entry.path();
-            if path.is_dir() {
-                stack.push(path);
-                continue;
             }
-
-            if path.is_file() {
-                let _ = entry_tx.send(path);
-            }
-        }
-    }
+            WalkState::Continue
+        })
+    });
 }
 
 /// Generates a hash map of file/path entries from all files sent to the
@@ -113,46 +274,62 @@ fn build_file_map(entry_rx: Receiver<PathBuf>) -> HashMap<PathBuf, PathBuf> {
     tree
 }
 
-/// Searches an `msbuild.log` for all lines containing `s` string and sends
-/// them out on the `tx` channel.
-fn find_all_lines(reader: BufReader<File>, s: &str, tx: Sender<String>) {
+/// Searches an `msbuild.log` for all lines containing one of the `compilers`
+/// substrings and sends them out on the `tx` channel, tagged with whichever
+/// compiler matched. When a line matches more than one `compilers` entry
+/// (e.g. `cl.exe` and `clang-cl.exe` both match a `clang-cl.exe` invocation,
+/// since the former is a substring of the latter), the longest match wins,
+/// since that's the one that was actually invoked.
+fn find_all_lines<R: BufRead>(reader: R, compilers: &[String], tx: Sender<(String, String)>) {
     reader.lines().map_while(Result::ok).for_each(|line| {
-        if line.to_lowercase().contains(s) {
-            let _ = tx.send(line);
+        let lower = line.to_lowercase();
+        let compiler = compilers
+            .iter()
+            .filter(|c| lower.contains(c.as_str()))
+            .max_by_key(|c| c.len());
+        if let Some(compiler) = compiler {
+            let _ = tx.send((line, compiler.clone()));
         }
     });
 }
 
-/// Listens on the `rx` channel for strings and strips them of all superfluous
-/// characters.  Sends the updated string on the `tx` channel.
-fn cleanup_line(rx: Receiver<String>, tx: Sender<String>) {
-    while let Ok(s) = rx.recv() {
+/// Listens on the `rx` channel for (line, compiler) pairs and strips the line
+/// of all superfluous characters.  Sends the updated pair on the `tx`
+/// channel.
+fn cleanup_line(
+    rx: Receiver<(String, String)>,
+    tx: Sender<(String, String)>,
+) {
+    while let Ok((s, compiler)) = rx.recv() {
         let s = s.replace("\"", "");
-        let _ = tx.send(s);
+        let _ = tx.send((s, compiler));
     }
 }
 
-/// Converts strings received on the `rx` channel into tokens and sends them out
-/// on the `tx` channel.
-fn tokenize_lines(rx: Receiver<String>, tx: Sender<Vec<String>>) {
-    while let Ok(s) = rx.recv() {
+/// Converts (line, compiler) pairs received on the `rx` channel into
+/// (tokens, compiler) pairs and sends them out on the `tx` channel.
+fn tokenize_lines(
+    rx: Receiver<(String, String)>,
+    tx: Sender<(Vec<String>, String)>,
+) {
+    while let Ok((s, compiler)) = rx.recv() {
         let t: Vec<_> = s.split_whitespace().map(String::from).collect();
-        let _ = tx.send(t);
+        let _ = tx.send((t, compiler));
     }
 }
 
-/// Converts a stream of tokens received on the `rx` channel into a
-/// `CompileCommand` and sends it out on the `tx` channel. The `map` generated
-/// by `build_file_map` is used to find the paths to any source files that did
-/// not include it in `msbuild.log`. Errors are reported on the `error_tx`
-/// channel
+/// Converts a stream of (tokens, compiler) pairs received on the `rx`
+/// channel into a `CompileCommand` and sends it out on the `tx` channel. The
+/// `map` generated by `build_file_map` is used to find the paths to any
+/// source files that did not include it in `msbuild.log`. Errors are
+/// reported on the `error_tx` channel
 fn create_compile_commands(
     map: HashMap<PathBuf, PathBuf>,
-    rx: Receiver<Vec<String>>,
+    rx: Receiver<(Vec<String>, String)>,
     tx: Sender<CompileCommand>,
     error_tx: Sender<String>,
 ) {
-    while let Ok(t) = rx.recv() {
+    while let Ok((t, compiler)) = rx.recv() {
         let path = match t.last() {
             Some(path) => Path::new(path),
             None => {
@@ -196,12 +373,150 @@ fn create_compile_commands(
             file: file_name,
             directory: PathBuf::from(parent),
             arguments: t,
+            compiler,
         };
 
         let _ = tx.send(cc);
     }
 }
 
+/// Rewrites `cc.directory` to absolute or relative form; `cc.file` is always
+/// a bare file name and is left untouched.
+///
+/// `--path-style absolute` canonicalizes `cc.directory` outright.
+/// `--path-style relative` rebases `cc.directory` against `base`, canonicalizing
+/// both first — this matters even when `cc.directory` is itself already
+/// relative, since a relative path is only ever relative to the current
+/// working directory, which is not necessarily `base` (falls back to `.`
+/// rather than an empty path when the two are equal). Falls back to the
+/// uncanonicalized path if a directory no longer exists on disk.
+fn apply_path_style(cc: &mut CompileCommand, style: PathStyle, base: &Path) {
+    match style {
+        PathStyle::Absolute => {
+            cc.directory = cc
+                .directory
+                .canonicalize()
+                .unwrap_or_else(|_| cc.directory.clone());
+        }
+        PathStyle::Relative => {
+            let directory = cc
+                .directory
+                .canonicalize()
+                .unwrap_or_else(|_| cc.directory.clone());
+            let base = base.canonicalize().unwrap_or_else(|_| base.to_path_buf());
+            cc.directory = match directory.strip_prefix(&base) {
+                Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => directory,
+            };
+        }
+    }
+}
+
+/// Sorts `compile_commands` by file name, then by directory, so output is
+/// deterministic run-to-run unless `--no-sort` is passed.
+fn sort_compile_commands(compile_commands: &mut [CompileCommand]) {
+    compile_commands.sort_by(|a, b| {
+        a.file
+            .cmp(&b.file)
+            .then_with(|| a.directory.cmp(&b.directory))
+    });
+}
+
+/// Resolves `template`'s placeholder tokens against `cc`, splitting the
+/// template on whitespace first so each token is substituted independently.
+/// `{args}` expands to the full captured arguments vector as separate
+/// tokens; the others (`{}`, `{/}`, `{//}`, `{.}`, `{compiler}`) substitute
+/// in place.
+fn resolve_template(template: &str, cc: &CompileCommand) -> Vec<String> {
+    let full_path = cc.directory.join(&cc.file);
+    let full = full_path.to_string_lossy();
+    let bare = cc.file.to_string_lossy();
+    let dir = cc.directory.to_string_lossy();
+    let stem = full_path.with_extension("");
+    let stem = stem.to_string_lossy();
+
+    template
+        .split_whitespace()
+        .flat_map(|token| {
+            if token == "{args}" {
+                cc.arguments.clone()
+            } else {
+                vec![
+                    token
+                        .replace("{//}", &dir)
+                        .replace("{.}", &stem)
+                        .replace("{/}", &bare)
+                        .replace("{}", &full)
+                        .replace("{compiler}", &cc.compiler),
+                ]
+            }
+        })
+        .collect()
+}
+
+/// Prints a child process's captured stdout/stderr under a shared lock so
+/// output from concurrent `--exec` jobs isn't interleaved.
+fn print_output(print_lock: &Mutex<()>, file: &Path, output: &Output) {
+    let _guard = print_lock.lock().unwrap();
+    println!("--- {file:?} ---");
+    if !output.stdout.is_empty() {
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+    }
+    if !output.stderr.is_empty() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    }
+}
+
+/// Runs `template` once per entry in `compile_commands`, using a bounded pool
+/// of `threads` workers to drive the invocations in parallel. Returns `true`
+/// if every invocation exited successfully.
+fn run_exec(
+    template: &str,
+    threads: usize,
+    compile_commands: &[CompileCommand],
+) -> bool {
+    let print_lock = Mutex::new(());
+    let all_ok = AtomicBool::new(true);
+    let (job_tx, job_rx) = bounded::<&CompileCommand>(CHANNEL_CAPACITY);
+
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            let job_rx = job_rx.clone();
+            let print_lock = &print_lock;
+            let all_ok = &all_ok;
+            s.spawn(move || {
+                while let Ok(cc) = job_rx.recv() {
+                    let argv = resolve_template(template, cc);
+                    let Some((program, args)) = argv.split_first() else {
+                        continue;
+                    };
+                    match Command::new(program).args(args).output() {
+                        Ok(output) => {
+                            print_output(print_lock, &cc.file, &output);
+                            if !output.status.success() {
+                                all_ok.store(false, Ordering::Relaxed);
+                            }
+                        }
+                        Err(e) => {
+                            let _guard = print_lock.lock().unwrap();
+                            eprintln!("Failed to run {program:?}: {e}");
+                            all_ok.store(false, Ordering::Relaxed);
+                        }
+                    }
+                }
+            });
+        }
+
+        for cc in compile_commands {
+            let _ = job_tx.send(cc);
+        }
+        drop(job_tx);
+    });
+
+    all_ok.load(Ordering::Relaxed)
+}
+
 fn main() -> Result<()> {
     // Parse command line arguments
     let cli = Cli::parse();
@@ -229,12 +544,15 @@ fn main() -> Result<()> {
         .open(&cli.output_file)
         .with_context(|| format!("Failed to open {:?}", cli.output_file))?;
 
+    let overrides =
+        build_overrides(&cli.source_directory, &cli.extensions, &cli.exclude)?;
+
     println!(
         "Preparing to generate the lookup tree (this will take some time) ..."
     );
     let tree = thread::scope(|s| {
-        let (entry_tx, entry_rx) = channel();
-        let (error_tx, error_rx) = channel();
+        let (entry_tx, entry_rx) = bounded(CHANNEL_CAPACITY);
+        let (error_tx, error_rx) = bounded(CHANNEL_CAPACITY);
 
         // Separate thread for error handling.
         s.spawn(move || {
@@ -249,9 +567,16 @@ fn main() -> Result<()> {
             build_file_map(entry_rx)
         });
 
-        // Traverse the directory tree
+        // Traverse the directory tree in parallel
         println!("Directory thraversal thread initialized.");
-        find_all_files(cli.source_directory, entry_tx, error_tx);
+        find_all_files(
+            cli.source_directory.clone(),
+            overrides,
+            cli.no_ignore,
+            cli.hidden,
+            entry_tx,
+            error_tx,
+        );
 
         // Return the tree to the main thread
         h.join().unwrap()
@@ -262,12 +587,12 @@ fn main() -> Result<()> {
         "Preparing to generate {:?} (this will take some time) ...",
         cli.output_file
     );
-    thread::scope(|s| {
-        let (source_tx, source_rx) = channel();
-        let (preprocess_tx, preprocess_rx) = channel();
-        let (token_tx, token_rx) = channel();
-        let (compile_command_tx, compile_command_rx) = channel();
-        let (error_tx, error_rx) = channel();
+    let exec_ok = thread::scope(|s| {
+        let (source_tx, source_rx) = bounded(CHANNEL_CAPACITY);
+        let (preprocess_tx, preprocess_rx) = bounded(CHANNEL_CAPACITY);
+        let (token_tx, token_rx) = bounded(CHANNEL_CAPACITY);
+        let (compile_command_tx, compile_command_rx) = bounded(CHANNEL_CAPACITY);
+        let (error_tx, error_rx) = bounded(CHANNEL_CAPACITY);
 
         // Separate thread for error handling.
         s.spawn(move || {
@@ -314,12 +639,248 @@ fn main() -> Result<()> {
 
         // Generate the compile_commands.json file
         println!("Waiting for compile commands ...",);
-        let compile_commands: Vec<_> = compile_command_rx.iter().collect();
+        let mut compile_commands: Vec<_> = compile_command_rx.iter().collect();
+
+        // Run `--exec` before `--path-style` rewrites `directory` below, so
+        // its `{}`/`{//}` placeholders resolve against the directories as
+        // the tree walk/`msbuild.log` actually produced them (relative to
+        // the process's working directory), not against `--path-base`.
+        let exec_ok = match &cli.exec {
+            Some(template) => {
+                println!(
+                    "Running {template:?} over {} compile commands ...",
+                    compile_commands.len()
+                );
+                run_exec(template, cli.threads, &compile_commands)
+            }
+            None => true,
+        };
+
+        let path_base = cli
+            .path_base
+            .clone()
+            .unwrap_or_else(|| cli.source_directory.clone());
+        for cc in &mut compile_commands {
+            apply_path_style(cc, cli.path_style, &path_base);
+        }
+
+        if !cli.no_sort {
+            sort_compile_commands(&mut compile_commands);
+        }
         println!("Writing {:?} database ...", cli.output_file);
-        let _ =
-            serde_json::to_writer_pretty(output_file_handle, &compile_commands);
+        let entries: Vec<_> = compile_commands
+            .iter()
+            .map(|cc| CompileCommandEntry::new(cc, cli.format))
+            .collect();
+        let _ = serde_json::to_writer_pretty(output_file_handle, &entries);
+
+        exec_ok
     });
     println!("Finished");
 
+    ensure!(exec_ok, "One or more --exec invocations failed");
+
     Ok(())
 }
+
+#[cfg(test)]
+mod path_style_tests {
+    use super::*;
+    use std::fs;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("msccjson-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn compile_command(directory: PathBuf) -> CompileCommand {
+        CompileCommand {
+            file: PathBuf::from("foo.cpp"),
+            directory,
+            arguments: Vec::new(),
+            compiler: String::from("cl.exe"),
+        }
+    }
+
+    #[test]
+    fn relative_source_directory_is_rebased_against_base() {
+        // `directory` already includes the relative `--source-directory`
+        // prefix (as the tree walk would produce it); it must still be
+        // stripped down to be relative to `base`, the same as the
+        // equivalent absolute `--source-directory` would produce.
+        let mut cc = compile_command(PathBuf::from("msccjson-nonexistent/sub"));
+        apply_path_style(&mut cc, PathStyle::Relative, Path::new("msccjson-nonexistent"));
+        assert_eq!(cc.directory, PathBuf::from("sub"));
+        assert_eq!(cc.file, PathBuf::from("foo.cpp"));
+    }
+
+    #[test]
+    fn absolute_source_directory_is_made_relative_to_base() {
+        let root = scratch_dir("nested");
+        fs::create_dir_all(root.join("sub")).unwrap();
+
+        let mut cc = compile_command(root.join("sub"));
+        apply_path_style(&mut cc, PathStyle::Relative, &root);
+
+        let _ = fs::remove_dir_all(&root);
+        assert_eq!(cc.directory, PathBuf::from("sub"));
+    }
+
+    #[test]
+    fn file_directly_in_source_root_is_relative_dot() {
+        let root = scratch_dir("root-file");
+
+        let mut cc = compile_command(root.clone());
+        apply_path_style(&mut cc, PathStyle::Relative, &root);
+
+        let _ = fs::remove_dir_all(&root);
+        assert_eq!(cc.directory, PathBuf::from("."));
+    }
+
+    #[test]
+    fn absolute_style_canonicalizes_directory() {
+        let root = scratch_dir("absolute");
+
+        let mut cc = compile_command(root.clone());
+        apply_path_style(&mut cc, PathStyle::Absolute, Path::new("unused"));
+
+        let expected = root.canonicalize().unwrap();
+        let _ = fs::remove_dir_all(&root);
+        assert_eq!(cc.directory, expected);
+    }
+}
+
+#[cfg(test)]
+mod find_all_lines_tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn matches(log: &str, compilers: &[&str]) -> Vec<(String, String)> {
+        let compilers: Vec<String> = compilers.iter().map(|c| c.to_string()).collect();
+        let (tx, rx) = bounded(CHANNEL_CAPACITY);
+        find_all_lines(Cursor::new(log), &compilers, tx);
+        rx.iter().collect()
+    }
+
+    #[test]
+    fn longer_compiler_name_wins_over_a_substring_match() {
+        let found = matches(
+            "clang-cl.exe /c foo.cpp\n",
+            &["cl.exe", "clang-cl.exe"],
+        );
+        assert_eq!(found, vec![(
+            String::from("clang-cl.exe /c foo.cpp"),
+            String::from("clang-cl.exe"),
+        )]);
+    }
+
+    #[test]
+    fn shorter_compiler_name_still_matches_on_its_own() {
+        let found = matches("cl.exe /c foo.cpp\n", &["cl.exe", "clang-cl.exe"]);
+        assert_eq!(found, vec![(
+            String::from("cl.exe /c foo.cpp"),
+            String::from("cl.exe"),
+        )]);
+    }
+
+    #[test]
+    fn non_matching_line_is_dropped() {
+        let found = matches("gcc -c foo.cpp\n", &["cl.exe", "clang-cl.exe"]);
+        assert!(found.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod sort_compile_commands_tests {
+    use super::*;
+
+    fn compile_command(file: &str, directory: &str) -> CompileCommand {
+        CompileCommand {
+            file: PathBuf::from(file),
+            directory: PathBuf::from(directory),
+            arguments: Vec::new(),
+            compiler: String::from("cl.exe"),
+        }
+    }
+
+    #[test]
+    fn sorts_by_file_then_directory() {
+        let mut compile_commands = vec![
+            compile_command("b.cpp", "dir"),
+            compile_command("a.cpp", "z"),
+            compile_command("a.cpp", "a"),
+        ];
+        sort_compile_commands(&mut compile_commands);
+        let sorted: Vec<_> = compile_commands
+            .iter()
+            .map(|cc| (cc.file.clone(), cc.directory.clone()))
+            .collect();
+        assert_eq!(
+            sorted,
+            vec![
+                (PathBuf::from("a.cpp"), PathBuf::from("a")),
+                (PathBuf::from("a.cpp"), PathBuf::from("z")),
+                (PathBuf::from("b.cpp"), PathBuf::from("dir")),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod build_overrides_tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("msccjson-test-overrides-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn extensions_become_include_globs() {
+        let root = scratch_dir("ext");
+        let overrides = build_overrides(&root, &[String::from("cpp")], &[]).unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(overrides.matched("foo.cpp", false).is_whitelist());
+        assert!(!overrides.matched("foo.h", false).is_whitelist());
+    }
+
+    #[test]
+    fn exclude_globs_take_priority_over_include_globs() {
+        let root = scratch_dir("exclude");
+        let overrides = build_overrides(
+            &root,
+            &[String::from("cpp")],
+            &[String::from("generated/*.cpp")],
+        )
+        .unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(overrides.matched("foo.cpp", false).is_whitelist());
+        assert!(overrides.matched("generated/foo.cpp", false).is_ignore());
+    }
+
+    #[test]
+    fn no_filters_matches_everything() {
+        let root = scratch_dir("none");
+        let overrides = build_overrides(&root, &[], &[]).unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(overrides.matched("foo.cpp", false).is_none());
+    }
+
+    #[test]
+    fn invalid_extension_glob_is_an_error() {
+        let root = scratch_dir("invalid");
+        let result = build_overrides(&root, &[String::from("[")], &[]);
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(result.is_err());
+    }
+}